@@ -0,0 +1,192 @@
+use std::io::{self, Read};
+
+use bencode::{self, Bencode};
+use bencode::Bencode::{ByteString, Number, List, Dict};
+use url::{Url, Host};
+use url::percent_encoding::{percent_encode, FORM_URLENCODED_ENCODE_SET};
+use reqwest;
+
+use torrent::Torrent;
+
+/// The announce event reported to a tracker, per BEP 3.
+pub enum AnnounceEvent {
+  /// The first announce, when a download begins.
+  Started,
+  /// A graceful shutdown announce.
+  Stopped,
+  /// Sent once, when the download finishes.
+  Completed,
+  /// A regular interval announce.
+  Regular,
+}
+
+impl AnnounceEvent {
+  fn as_str(&self) -> Option<&'static str> {
+    match *self {
+      AnnounceEvent::Started    => Some("started"),
+      AnnounceEvent::Stopped    => Some("stopped"),
+      AnnounceEvent::Completed  => Some("completed"),
+      AnnounceEvent::Regular    => None,
+    }
+  }
+}
+
+/// A successful response from a tracker announce.
+#[derive(Debug)]
+pub struct AnnounceResponse {
+  /// The number of seconds the client should wait before re-announcing.
+  pub interval: i64,
+  /// The peers the tracker returned, as `(host, port)` pairs.
+  pub peers: Vec<(Host, u16)>,
+}
+
+#[derive(Debug)]
+pub enum AnnounceError {
+  /// The torrent has no trackers to announce to.
+  NoTrackers,
+  /// The HTTP request failed.
+  Http(reqwest::Error),
+  /// Reading the response body failed.
+  Io(io::Error),
+  /// The response was not valid bencode.
+  InvalidBencode(bencode::streaming::Error),
+  /// The response was not a bencode dict.
+  NotADict,
+  /// The tracker returned a `failure reason`.
+  Failure(String),
+  /// The response was missing or had a malformed `interval`.
+  InvalidInterval,
+  /// The `peers` field was missing or malformed.
+  InvalidPeers,
+}
+
+fn parse_peer_dict(peers_be: &[Bencode]) -> Result<Vec<(Host, u16)>, AnnounceError> {
+  let mut peers: Vec<(Host, u16)> = Vec::new();
+  for peer_be in peers_be.iter() {
+    let peer = match peer_be {
+      &Dict(ref d)  => d,
+      _             => return Err(AnnounceError::InvalidPeers),
+    };
+    let host = match peer.get(&b"ip"[..]) {
+      Some(&ByteString(ref ip)) => match ::std::str::from_utf8(&ip[..]).ok().and_then(|s| Host::parse(s).ok()) {
+        Some(h) => h,
+        None    => return Err(AnnounceError::InvalidPeers),
+      },
+      _                         => return Err(AnnounceError::InvalidPeers),
+    };
+    let port = match peer.get(&b"port"[..]) {
+      Some(&Number(n)) if n >= 0 && n <= 65535 => n as u16,
+      _                                        => return Err(AnnounceError::InvalidPeers),
+    };
+    peers.push((host, port));
+  };
+  Ok(peers)
+}
+
+fn parse_peer_compact(buf: &[u8]) -> Result<Vec<(Host, u16)>, AnnounceError> {
+  if buf.len() % 6 != 0 {
+    return Err(AnnounceError::InvalidPeers);
+  }
+  let mut peers: Vec<(Host, u16)> = Vec::new();
+  let mut chunk = &buf[..];
+  while chunk.len() >= 6 {
+    let host = match Host::parse(&format!("{}.{}.{}.{}", chunk[0], chunk[1], chunk[2], chunk[3])) {
+      Ok(h)   => h,
+      Err(_)  => return Err(AnnounceError::InvalidPeers),
+    };
+    let port = ((chunk[4] as u16) << 8) | chunk[5] as u16;
+    peers.push((host, port));
+    chunk = &chunk[6 ..];
+  };
+  Ok(peers)
+}
+
+fn parse_response(body: &[u8]) -> Result<AnnounceResponse, AnnounceError> {
+  let ben = match bencode::from_buffer(body) {
+    Ok(b)   => b,
+    Err(e)  => return Err(AnnounceError::InvalidBencode(e)),
+  };
+  let dict = match ben {
+    Dict(ref d) => d.clone(),
+    _           => return Err(AnnounceError::NotADict),
+  };
+
+  if let Some(&ByteString(ref reason)) = dict.get(&b"failure reason"[..]) {
+    return Err(AnnounceError::Failure(String::from_utf8_lossy(&reason[..]).into_owned()));
+  };
+
+  let interval = match dict.get(&b"interval"[..]) {
+    Some(&Number(n))  => n,
+    _                 => return Err(AnnounceError::InvalidInterval),
+  };
+
+  let peers = match dict.get(&b"peers"[..]) {
+    Some(&List(ref l))        => try!(parse_peer_dict(l)),
+    Some(&ByteString(ref s))  => try!(parse_peer_compact(&s[..])),
+    _                         => return Err(AnnounceError::InvalidPeers),
+  };
+
+  Ok(AnnounceResponse {
+    interval: interval,
+    peers:    peers,
+  })
+}
+
+fn announce_tracker(tracker: &Url, query: &str) -> Result<AnnounceResponse, AnnounceError> {
+  let base = tracker.serialize();
+  // Trackers may already carry a query string (e.g. passkey URLs); append with
+  // `&` in that case rather than adding a second `?`.
+  let sep = if base.contains('?') { "&" } else { "?" };
+  let url = format!("{}{}{}", base, sep, query);
+  let mut resp = match reqwest::get(&url) {
+    Ok(r)   => r,
+    Err(e)  => return Err(AnnounceError::Http(e)),
+  };
+  let mut body: Vec<u8> = Vec::new();
+  match resp.read_to_end(&mut body) {
+    Ok(_)   => (),
+    Err(e)  => return Err(AnnounceError::Io(e)),
+  };
+  parse_response(&body[..])
+}
+
+impl Torrent {
+  /// Announce this torrent to its trackers and return the first successful
+  /// response. Tiers in `trackers` are tried in order; within a tier the
+  /// trackers are tried left to right until one answers.
+  ///
+  /// This is only available with the `net` feature enabled.
+  pub fn announce(&self,
+                  peer_id: &[u8; 20],
+                  port: u16,
+                  uploaded: u64,
+                  downloaded: u64,
+                  left: u64,
+                  event: AnnounceEvent)
+                  -> Result<AnnounceResponse, AnnounceError> {
+    let mut query = String::new();
+    query.push_str("info_hash=");
+    query.push_str(&percent_encode(&self.info_hash.hash[..], FORM_URLENCODED_ENCODE_SET));
+    query.push_str("&peer_id=");
+    query.push_str(&percent_encode(&peer_id[..], FORM_URLENCODED_ENCODE_SET));
+    query.push_str(&format!("&port={}", port));
+    query.push_str(&format!("&uploaded={}", uploaded));
+    query.push_str(&format!("&downloaded={}", downloaded));
+    query.push_str(&format!("&left={}", left));
+    query.push_str("&compact=1");
+    if let Some(e) = event.as_str() {
+      query.push_str(&format!("&event={}", e));
+    };
+
+    let mut last = AnnounceError::NoTrackers;
+    for tier in self.trackers.iter() {
+      for tracker in tier.iter() {
+        match announce_tracker(tracker, &query) {
+          Ok(resp)  => return Ok(resp),
+          Err(e)    => last = e,
+        };
+      };
+    };
+    Err(last)
+  }
+}
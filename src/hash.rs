@@ -1,10 +1,18 @@
 use std::fmt;
 use std::mem::uninitialized;
 
+use sha1::Sha1;
+use sha2::{Sha256, Digest};
+
 pub struct Sha1Hash {
   pub hash: [u8; 20],
 }
 
+#[derive(PartialEq, Eq, Hash)]
+pub struct Sha256Hash {
+  pub hash: [u8; 32],
+}
+
 #[derive(Debug)]
 pub struct InvalidHashLength(pub usize);
 
@@ -25,6 +33,19 @@ impl Sha1Hash {
       false => Err(InvalidHashLength(s.len())),
     }
   }
+
+  /// SHA-1 the given bytes and return the resulting 160-bit digest.
+  pub fn compute(s: &[u8]) -> Sha1Hash {
+    let mut m = Sha1::new();
+    m.update(s);
+    let mut hash: [u8; 20] = unsafe { uninitialized() };
+    for (d, s) in hash.iter_mut().zip(m.digest().bytes().iter()) {
+      *d = *s;
+    };
+    Sha1Hash {
+      hash: hash,
+    }
+  }
 }
 
 impl fmt::Debug for Sha1Hash {
@@ -36,3 +57,44 @@ impl fmt::Debug for Sha1Hash {
   }
 }
 
+impl Sha256Hash {
+  /// Create a `Sha256Hash` from a slice. Returns `Err(InvalidHashLength)` if
+  /// the slice is not 256 bits (32 bytes) long.
+  pub fn from_buffer(s: &[u8]) -> Result<Sha256Hash, InvalidHashLength> {
+    match s.len() == 32 {
+      true  => {
+        let mut hash: [u8; 32] = unsafe { uninitialized() };
+        for (d, s) in hash.iter_mut().zip(s.iter()) {
+          *d = *s;
+        };
+        Ok(Sha256Hash {
+          hash: hash,
+        })
+      },
+      false => Err(InvalidHashLength(s.len())),
+    }
+  }
+
+  /// SHA-256 the given bytes and return the resulting 256-bit digest.
+  pub fn compute(s: &[u8]) -> Sha256Hash {
+    let mut m = Sha256::new();
+    m.input(s);
+    let mut hash: [u8; 32] = unsafe { uninitialized() };
+    for (d, s) in hash.iter_mut().zip(m.result().iter()) {
+      *d = *s;
+    };
+    Sha256Hash {
+      hash: hash,
+    }
+  }
+}
+
+impl fmt::Debug for Sha256Hash {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for b in self.hash.iter() {
+      try!(write!(f, "{:02x}", *b));
+    }
+    Ok(())
+  }
+}
+
@@ -35,9 +35,18 @@
 
 extern crate bencode;
 extern crate url;
+extern crate sha1;
+extern crate sha2;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(feature = "net")]
+extern crate reqwest;
 
-pub use torrent::{Torrent, TorrentDirTreeNode};
-pub use hash::Sha1Hash;
+pub use torrent::{Torrent, TorrentDirTreeNode, VerifyReport, PieceResult, FilePieceRange};
+pub use torrent::TorrentVersion;
+pub use hash::{Sha1Hash, Sha256Hash};
+#[cfg(feature = "net")]
+pub use announce::{AnnounceResponse, AnnounceError, AnnounceEvent};
 
 macro_rules! try_opt (
   ($ex:expr)  => (match $ex {
@@ -55,4 +64,6 @@ macro_rules! try_case (
 
 mod hash;
 mod torrent;
+#[cfg(feature = "net")]
+mod announce;
 
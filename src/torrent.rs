@@ -1,16 +1,19 @@
 use std::vec::Vec;
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::io::{Seek, SeekFrom};
 use std::collections::HashMap;
 use std::str::{from_utf8, Utf8Error};
 use std::num::ToPrimitive;
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
+use std::collections::BTreeMap;
 
 use bencode::{self, Bencode, FromBencode};
 use bencode::Bencode::{ByteString, Number, List, Dict};
+use bencode::util::ByteString as BString;
 use url::{self, Url, Host};
 
-use hash::{Sha1Hash, InvalidHashLength};
+use hash::{Sha1Hash, Sha256Hash, InvalidHashLength};
 
 use self::TorrentDirTreeNode::{FileNode, DirNode};
 
@@ -46,19 +49,93 @@ pub struct Torrent {
   pub filename: String,
   /// The directory structure of the torrent.
   pub contents: TorrentDirTreeNode,
+  /// The torrent's creation time, as a Unix timestamp (seconds since the
+  /// epoch). See `creation_datetime` for a `chrono` view of this field.
+  pub creation_date: Option<i64>,
+  /// The name and version of the program that created the torrent.
+  pub created_by: Option<String>,
+  /// A free-form comment embedded in the torrent.
+  pub comment: Option<String>,
+  /// The character encoding used for the strings in the torrent.
+  pub encoding: Option<String>,
+  /// The info-hash of the torrent: the SHA-1 digest of the bencoded `info`
+  /// dictionary. This is the identifier used in tracker announces and magnet
+  /// links.
+  pub info_hash: Sha1Hash,
+  /// Which BitTorrent metadata version(s) this torrent uses. See
+  /// [BEP 52](http://www.bittorrent.org/beps/bep_0052.html).
+  pub version: TorrentVersion,
+  /// The v2 info-hash: the SHA-256 digest of the bencoded `info` dictionary.
+  /// Present for v2 and hybrid torrents.
+  pub info_hash_v2: Option<Sha256Hash>,
+  /// The BEP 52 `piece layers`: a map from each file's SHA-256 `pieces root`
+  /// to the concatenated 32-byte hashes of that file's leaf pieces. Present
+  /// for v2 and hybrid torrents.
+  pub piece_layers: Option<HashMap<Sha256Hash, Vec<Sha256Hash>>>,
+}
+
+/// The BitTorrent metadata version of a torrent.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TorrentVersion {
+  /// A classic (BEP 3) torrent with SHA-1 `pieces`.
+  V1,
+  /// A v2-only (BEP 52) torrent with a SHA-256 `file tree`.
+  V2,
+  /// A hybrid torrent carrying both the v1 and v2 structures.
+  Hybrid,
 }
 
 /// A node in a directory structure.
 #[derive(Debug)]
 pub enum TorrentDirTreeNode {
-  /// A file node in a directory structure. `FileNode(n)` represents a file of
-  /// size `n`.
-  FileNode(u64),
+  /// A file node in a directory structure. `FileNode(n, root)` represents a
+  /// file of size `n`; `root` is the BEP 52 SHA-256 merkle `pieces root` for
+  /// the file, present only for v2-only torrents. A hybrid torrent's
+  /// `contents` comes from its v1 `length`/`files`, so its roots are `None`.
+  FileNode(u64, Option<Sha256Hash>),
   /// A directory node in a directory structure. A map of filenames to
   /// directories and/or files.
   DirNode(HashMap<String, TorrentDirTreeNode>),
 }
 
+/// The span of a single file covered by a piece, as a byte range *within that
+/// file*.
+#[derive(Debug)]
+pub struct FilePieceRange {
+  /// The file's path, rooted at the torrent's top-level name.
+  pub path: PathBuf,
+  /// The offset of the first covered byte within the file.
+  pub start: u64,
+  /// The offset one past the last covered byte within the file.
+  pub end: u64,
+}
+
+/// The outcome of checking a single piece against the data on disk.
+#[derive(Debug)]
+pub struct PieceResult {
+  /// The index of this piece in `Torrent::pieces`.
+  pub index: usize,
+  /// `true` if the data hashed to the expected piece digest.
+  pub matched: bool,
+  /// The file(s) and byte ranges this piece spans, so a failing piece can be
+  /// traced back to the corrupt file.
+  pub files: Vec<FilePieceRange>,
+}
+
+/// The result of verifying a torrent's data against a directory on disk.
+#[derive(Debug)]
+pub struct VerifyReport {
+  /// The per-piece results, in piece order.
+  pub pieces: Vec<PieceResult>,
+}
+
+impl VerifyReport {
+  /// `true` if every piece matched.
+  pub fn is_complete(&self) -> bool {
+    self.pieces.iter().all(|p| p.matched)
+  }
+}
+
 #[derive(Debug)]
 pub enum TorrentFromBencodeError {
   NotADict,
@@ -85,6 +162,13 @@ pub enum TorrentFromBencodeError {
   HttpSeedNotAString,
   HttpSeedParseError(url::ParseError),
   HttpSeedInvalidUtf8(Utf8Error),
+  CreationDateNotANumber,
+  CreatedByNotAString,
+  CreatedByInvalidUtf8(Utf8Error),
+  CommentNotAString,
+  CommentInvalidUtf8(Utf8Error),
+  EncodingNotAString,
+  EncodingInvalidUtf8(Utf8Error),
   InfoDictNotADict,
   RootHashNotAString,
   RootHashInvalidHashLength(usize),
@@ -114,6 +198,164 @@ pub enum TorrentFromBencodeError {
   FileNameInvalidUtf8(Utf8Error),
   EmptyFilePath,
   DuplicateFileName,
+  MetaVersionNotANumber,
+  UnsupportedMetaVersion(i64),
+  FileTreeNotADict,
+  FileTreeEntryNotADict,
+  FileTreeNameInvalidUtf8(Utf8Error),
+  FileTreeLengthNotANumber,
+  FileTreeLengthOutOfRange,
+  FileTreeLengthNotPresent,
+  PiecesRootNotAString,
+  PiecesRootInvalidHashLength(usize),
+  PiecesRootNotPresent,
+  PieceLayersNotADict,
+  PieceLayersRootInvalidHashLength(usize),
+  PieceLayersValueNotAString,
+  PieceLayersInvalidLength(usize),
+}
+
+/// Encode a `Bencode` value into canonical bencode, appending to `out`.
+///
+/// Dict keys come out sorted lexicographically by their raw bytes (the
+/// `BTreeMap` backing a bencode dict already orders them this way) and
+/// integers are emitted without leading zeros, so the encoding is stable and
+/// suitable for hashing.
+fn encode_bencode(b: &Bencode, out: &mut Vec<u8>) {
+  match b {
+    &ByteString(ref v)  => {
+      out.push_all(format!("{}:", v.len()).as_bytes());
+      out.push_all(&v[..]);
+    },
+    &Number(n)          => {
+      out.push_all(format!("i{}e", n).as_bytes());
+    },
+    &List(ref l)        => {
+      out.push(b'l');
+      for e in l.iter() {
+        encode_bencode(e, out);
+      };
+      out.push(b'e');
+    },
+    &Dict(ref d)        => {
+      out.push(b'd');
+      for (k, v) in d.iter() {
+        out.push_all(format!("{}:", k.len()).as_bytes());
+        out.push_all(&k[..]);
+        encode_bencode(v, out);
+      };
+      out.push(b'e');
+    },
+  }
+}
+
+/// Find the end of the bencode value starting at `pos` in `buf`, returning the
+/// index one past its last byte. Returns `None` if `buf` ends before the value
+/// does or holds an unexpected byte where a value or string length starts; the
+/// digits of an integer are not checked.
+fn scan_value(buf: &[u8], pos: usize) -> Option<usize> {
+  match buf.get(pos) {
+    Some(&b'i')  => {
+      let mut i = pos + 1;
+      while try_opt!(buf.get(i)) != &b'e' {
+        i += 1;
+      }
+      Some(i + 1)
+    },
+    Some(&b'l') | Some(&b'd')  => {
+      let mut i = pos + 1;
+      while try_opt!(buf.get(i)) != &b'e' {
+        i = try_opt!(scan_value(buf, i));
+      }
+      Some(i + 1)
+    },
+    Some(&c) if c >= b'0' && c <= b'9'  => {
+      let mut i = pos;
+      let mut len: usize = 0;
+      loop {
+        let c = *try_opt!(buf.get(i));
+        if c == b':' {
+          break;
+        }
+        if c < b'0' || c > b'9' {
+          return None;
+        }
+        len = len * 10 + (c - b'0') as usize;
+        i += 1;
+      }
+      if i + 1 + len > buf.len() {
+        return None;
+      }
+      Some(i + 1 + len)
+    },
+    _   => None,
+  }
+}
+
+/// Locate the raw bytes of the top-level `info` value within a bencoded
+/// buffer, so its info-hash can be taken over the exact original encoding.
+fn find_info_span(buf: &[u8]) -> Option<&[u8]> {
+  if buf.get(0) != Some(&b'd') {
+    return None;
+  }
+  let mut pos = 1;
+  while try_opt!(buf.get(pos)) != &b'e' {
+    let key_end = try_opt!(scan_value(buf, pos));
+    // The key is a byte string; its contents sit just before `key_end`.
+    let colon = pos + try_opt!(buf[pos .. key_end].iter().position(|&c| c == b':')) + 1;
+    let key = &buf[colon .. key_end];
+    let val_end = try_opt!(scan_value(buf, key_end));
+    if key == b"info" {
+      return Some(&buf[key_end .. val_end]);
+    }
+    pos = val_end;
+  }
+  None
+}
+
+/// Parse a BEP 52 `file tree` dict into the crate's directory tree. Each file
+/// leaf — encoded as `{"": {"length": .., "pieces root": ..}}` — becomes a
+/// `FileNode` carrying its 32-byte SHA-256 `pieces root`.
+fn parse_file_tree(tree: &bencode::DictMap) -> Result<HashMap<String, TorrentDirTreeNode>, TorrentFromBencodeError> {
+  use self::TorrentFromBencodeError::*;
+
+  let mut entries: HashMap<String, TorrentDirTreeNode> = HashMap::new();
+  for (name_be, node_be) in tree.iter() {
+    let name = match from_utf8(&name_be[..]) {
+      Ok(ss)  => String::from_str(ss),
+      Err(e)  => return Err(FileTreeNameInvalidUtf8(e)),
+    };
+    let node = try_case!(Dict, node_be, FileTreeEntryNotADict);
+    match node.get(&b""[..]) {
+      Some(leaf_be) => {
+        let leaf = try_case!(Dict, leaf_be, FileTreeEntryNotADict);
+        let length = match leaf.get(&b"length"[..]) {
+          Some(l_be)  => match try_case!(Number, l_be, FileTreeLengthNotANumber).to_u64() {
+            Some(l) => l,
+            None    => return Err(FileTreeLengthOutOfRange),
+          },
+          None        => return Err(FileTreeLengthNotPresent),
+        };
+        let root = match leaf.get(&b"pieces root"[..]) {
+          Some(pr_be) => {
+            let pr = try_case!(ByteString, pr_be, PiecesRootNotAString);
+            match Sha256Hash::from_buffer(&pr[..]) {
+              Ok(h)   => h,
+              Err(e)  => match e {
+                InvalidHashLength(l) => return Err(PiecesRootInvalidHashLength(l)),
+              },
+            }
+          },
+          None        => return Err(PiecesRootNotPresent),
+        };
+        entries.insert(name, FileNode(length, Some(root)));
+      },
+      None          => {
+        entries.insert(name, DirNode(try!(parse_file_tree(node))));
+      },
+    };
+  };
+  Ok(entries)
 }
 
 impl FromBencode for Torrent {
@@ -233,11 +475,123 @@ impl FromBencode for Torrent {
       None  => Vec::new(),
     };
 
+    let creation_date = match hm.get(&b"creation date"[..]) {
+      Some(cd_be) => Some(*try_case!(Number, cd_be, CreationDateNotANumber)),
+      None        => None,
+    };
+
+    let created_by = match hm.get(&b"created by"[..]) {
+      Some(cb_be) => match from_utf8(&try_case!(ByteString, cb_be, CreatedByNotAString)[..]) {
+        Ok(ss)  => Some(String::from_str(ss)),
+        Err(e)  => return Err(CreatedByInvalidUtf8(e)),
+      },
+      None        => None,
+    };
+
+    let comment = match hm.get(&b"comment"[..]) {
+      Some(c_be)  => match from_utf8(&try_case!(ByteString, c_be, CommentNotAString)[..]) {
+        Ok(ss)  => Some(String::from_str(ss)),
+        Err(e)  => return Err(CommentInvalidUtf8(e)),
+      },
+      None        => None,
+    };
+
+    let encoding = match hm.get(&b"encoding"[..]) {
+      Some(e_be)  => match from_utf8(&try_case!(ByteString, e_be, EncodingNotAString)[..]) {
+        Ok(ss)  => Some(String::from_str(ss)),
+        Err(e)  => return Err(EncodingInvalidUtf8(e)),
+      },
+      None        => None,
+    };
+
+    let piece_layers = match hm.get(&b"piece layers"[..]) {
+      Some(pl_be) => {
+        let pl = try_case!(Dict, pl_be, PieceLayersNotADict);
+        let mut layers: HashMap<Sha256Hash, Vec<Sha256Hash>> = HashMap::new();
+        for (root_be, leaves_be) in pl.iter() {
+          let root = match Sha256Hash::from_buffer(&root_be[..]) {
+            Ok(h)   => h,
+            Err(e)  => match e {
+              InvalidHashLength(l) => return Err(PieceLayersRootInvalidHashLength(l)),
+            },
+          };
+          let leaves_bytes = try_case!(ByteString, leaves_be, PieceLayersValueNotAString);
+          if leaves_bytes.len() % 32 != 0 {
+            return Err(PieceLayersInvalidLength(leaves_bytes.len()));
+          }
+          let mut leaves: Vec<Sha256Hash> = Vec::new();
+          let mut remaining = &leaves_bytes[..];
+          while remaining.len() >= 32 {
+            leaves.push(Sha256Hash::from_buffer(&remaining[.. 32]).unwrap());
+            remaining = &remaining[32 ..];
+          };
+          layers.insert(root, leaves);
+        };
+        Some(layers)
+      },
+      None        => None,
+    };
+
     let info = match hm.get(&b"info"[..]) {
       Some(i) => try_case!(Dict, i, InfoDictNotADict),
       None    => hm,
     };
 
+    // Re-encode the info dict in canonical bencode and hash it. This is the
+    // fallback used when the original bytes aren't available; `from_buffer`
+    // overwrites `info_hash` with a digest of the exact on-disk bytes.
+    let info_hash = {
+      let info_be = match hm.get(&b"info"[..]) {
+        Some(i) => i,
+        None    => bencode,
+      };
+      let mut buf: Vec<u8> = Vec::new();
+      encode_bencode(info_be, &mut buf);
+      Sha1Hash::compute(&buf[..])
+    };
+
+    // BEP 52: a `meta version` of 2 and a `file tree` identify a v2 torrent.
+    // A torrent carrying both the v1 `pieces` and the v2 `file tree` is hybrid.
+    // Only version 2 is defined; reject anything else rather than silently
+    // treating it as v1.
+    match info.get(&b"meta version"[..]) {
+      Some(mv_be) => {
+        let mv = *try_case!(Number, mv_be, MetaVersionNotANumber);
+        if mv != 2 {
+          return Err(UnsupportedMetaVersion(mv));
+        }
+      },
+      None        => (),
+    };
+
+    let v2_tree = match info.get(&b"file tree"[..]) {
+      Some(ft_be) => {
+        let ft = try_case!(Dict, ft_be, FileTreeNotADict);
+        Some(DirNode(try!(parse_file_tree(ft))))
+      },
+      None        => None,
+    };
+
+    let has_v1 = info.get(&b"pieces"[..]).is_some();
+    let version = match (has_v1, v2_tree.is_some()) {
+      (true, true)  => TorrentVersion::Hybrid,
+      (false, true) => TorrentVersion::V2,
+      _             => TorrentVersion::V1,
+    };
+
+    // The v2 info-hash is the SHA-256 digest of the same bencoded info dict.
+    let info_hash_v2 = if v2_tree.is_some() {
+      let info_be = match hm.get(&b"info"[..]) {
+        Some(i) => i,
+        None    => bencode,
+      };
+      let mut buf: Vec<u8> = Vec::new();
+      encode_bencode(info_be, &mut buf);
+      Some(Sha256Hash::compute(&buf[..]))
+    } else {
+      None
+    };
+
     let merkle_root = match info.get(&b"root hash"[..]) {
       Some(mr_be) => {
         let mr = try_case!(ByteString, mr_be, RootHashNotAString);
@@ -275,26 +629,57 @@ impl FromBencode for Torrent {
       None        => return Err(PieceLengthNotPresent),
     };
 
-    let pieces = match info.get(&b"pieces"[..]) {
-      Some(p_be) => try_case!(ByteString, p_be, PiecesNotAString),
-      None       => return Err(PiecesNotPresent),
-    };
-
     let mut pieces_vec: Vec<Sha1Hash> = Vec::new();
-    let mut remaining = &pieces[..];
+    if has_v1 {
+      let pieces = match info.get(&b"pieces"[..]) {
+        Some(p_be) => try_case!(ByteString, p_be, PiecesNotAString),
+        None       => return Err(PiecesNotPresent),
+      };
 
-    loop {
-      if remaining.len() < 20 {
-        return Err(InvalidPiecesLength(pieces.len()));
-      }
-      pieces_vec.push(Sha1Hash::from_buffer(&remaining[.. 20]).unwrap());
-      remaining = &remaining[20 ..];
+      let mut remaining = &pieces[..];
+      loop {
+        if remaining.len() < 20 {
+          return Err(InvalidPiecesLength(pieces.len()));
+        }
+        pieces_vec.push(Sha1Hash::from_buffer(&remaining[.. 20]).unwrap());
+        remaining = &remaining[20 ..];
 
-      if remaining.len() == 0 {
-        break;
+        if remaining.len() == 0 {
+          break;
+        }
       }
-    }
-    
+    };
+
+    // A v2-only torrent has no v1 `length`/`files`; its contents come from the
+    // `file tree` parsed above. A dict with neither `pieces` nor a `file tree`
+    // is malformed, so report the missing v1 pieces as the baseline did.
+    if !has_v1 {
+      let contents = match v2_tree {
+        Some(t) => t,
+        None    => return Err(PiecesNotPresent),
+      };
+      return Ok(Torrent {
+        trackers:     trackers,
+        nodes:        nodes,
+        httpseeds:    httpseeds,
+        urllist:      urllist,
+        private:      private,
+        piece_length: piece_length,
+        pieces:       pieces_vec,
+        merkle_root:  merkle_root,
+        filename:     name,
+        contents:     contents,
+        creation_date: creation_date,
+        created_by:   created_by,
+        comment:      comment,
+        encoding:     encoding,
+        info_hash:    info_hash,
+        version:      version,
+        info_hash_v2: info_hash_v2,
+        piece_layers: piece_layers,
+      });
+    };
+
     match info.get(&b"length"[..]) {
       Some(l) => {
         let length = match try_case!(Number, l, LengthNotANumber).to_u64() {
@@ -311,7 +696,15 @@ impl FromBencode for Torrent {
           pieces:       pieces_vec,
           merkle_root:  merkle_root,
           filename:     name,
-          contents:     FileNode(length),
+          contents:     FileNode(length, None),
+          creation_date: creation_date,
+          created_by:   created_by,
+          comment:      comment,
+          encoding:     encoding,
+          info_hash:    info_hash,
+          version:      version,
+          info_hash_v2: info_hash_v2,
+          piece_layers: piece_layers,
         })
       },
       None    => {
@@ -356,7 +749,7 @@ impl FromBencode for Torrent {
                 Ok(ss)  => String::from_str(ss),
                 Err(e)  => return Err(FileNameInvalidUtf8(e))
               };
-              match dir.insert(fname, FileNode(length)) {
+              match dir.insert(fname, FileNode(length, None)) {
                 None    => (),
                 Some(_) => return Err(DuplicateFileName),
               };
@@ -375,6 +768,14 @@ impl FromBencode for Torrent {
           merkle_root:  merkle_root,
           filename:     name,
           contents:     DirNode(filetree),
+          creation_date: creation_date,
+          created_by:   created_by,
+          comment:      comment,
+          encoding:     encoding,
+          info_hash:    info_hash,
+          version:      version,
+          info_hash_v2: info_hash_v2,
+          piece_layers: piece_layers,
         })
       }
     }
@@ -422,7 +823,290 @@ impl Torrent {
       Ok(d)   => d,
       Err(e)  => return Err(FromBufferError::InvalidBencode(e)),
     };
-    FromBencode::from_bencode(&ben).map_err(FromBufferError::FromBencode)
+    let mut torrent = try!(FromBencode::from_bencode(&ben).map_err(FromBufferError::FromBencode));
+    // Prefer hashing the exact original bytes of the info dict over the
+    // canonical re-encoding produced by `from_bencode`.
+    if let Some(span) = find_info_span(s) {
+      torrent.info_hash = Sha1Hash::compute(span);
+      if torrent.info_hash_v2.is_some() {
+        torrent.info_hash_v2 = Some(Sha256Hash::compute(span));
+      };
+    };
+    Ok(torrent)
+  }
+
+  /// Build a `magnet:` link for this torrent. The link carries the info-hash
+  /// (`xt`), display name (`dn`), every tracker across all tiers (`tr`), and
+  /// any web seeds (`ws`) and acceptable sources (`as`).
+  pub fn magnet_link(&self) -> String {
+    use url::percent_encoding::{utf8_percent_encode, FORM_URLENCODED_ENCODE_SET};
+
+    let mut s = String::new();
+    s.push_str("magnet:?xt=urn:btih:");
+    s.push_str(&format!("{:?}", self.info_hash));
+    s.push_str("&dn=");
+    s.push_str(&utf8_percent_encode(&self.filename[..], FORM_URLENCODED_ENCODE_SET));
+    for tier in self.trackers.iter() {
+      for tracker in tier.iter() {
+        s.push_str("&tr=");
+        s.push_str(&utf8_percent_encode(&tracker.serialize()[..], FORM_URLENCODED_ENCODE_SET));
+      };
+    };
+    if let Some(ref ws) = self.urllist {
+      s.push_str("&ws=");
+      s.push_str(&utf8_percent_encode(&ws.serialize()[..], FORM_URLENCODED_ENCODE_SET));
+    };
+    for seed in self.httpseeds.iter() {
+      s.push_str("&as=");
+      s.push_str(&utf8_percent_encode(&seed.serialize()[..], FORM_URLENCODED_ENCODE_SET));
+    };
+    s
+  }
+
+  /// Reconstruct the bencode representation of this torrent. The info dict's
+  /// keys are emitted in canonical (sorted) order so that re-encoding yields a
+  /// stable info-hash.
+  ///
+  /// Only v1 torrents can be serialized; the v2 `file tree`/`piece layers`
+  /// structures aren't reconstructed, so `None` is returned for v2 and hybrid
+  /// torrents rather than writing a wrong, v1-shaped dict.
+  pub fn to_bencode(&self) -> Option<Bencode> {
+    if self.version != TorrentVersion::V1 {
+      return None;
+    }
+
+    fn bs(b: &[u8]) -> BString {
+      BString::from_slice(b)
+    }
+
+    // The info dict.
+    let mut info: BTreeMap<BString, Bencode> = BTreeMap::new();
+    info.insert(bs(b"name"), ByteString(self.filename.clone().into_bytes()));
+    info.insert(bs(b"piece length"), Number(self.piece_length as i64));
+
+    let mut pieces: Vec<u8> = Vec::new();
+    for piece in self.pieces.iter() {
+      pieces.push_all(&piece.hash[..]);
+    };
+    info.insert(bs(b"pieces"), ByteString(pieces));
+
+    if let Some(ref root) = self.merkle_root {
+      info.insert(bs(b"root hash"), ByteString(root.hash.to_vec()));
+    };
+    if self.private {
+      info.insert(bs(b"private"), Number(1));
+    };
+
+    match self.contents {
+      FileNode(length, _)   => {
+        info.insert(bs(b"length"), Number(length as i64));
+      },
+      DirNode(ref entries)  => {
+        // Walk the directory tree back into the flat `files` list, each entry
+        // carrying its `length` and full `path` from the torrent root.
+        fn walk(node: &TorrentDirTreeNode, prefix: &mut Vec<String>, out: &mut Vec<Bencode>) {
+          match node {
+            &FileNode(length, _)  => {
+              let mut f: BTreeMap<BString, Bencode> = BTreeMap::new();
+              f.insert(BString::from_slice(b"length"), Number(length as i64));
+              let path: Vec<Bencode> = prefix.iter()
+                .map(|c| ByteString(c.clone().into_bytes()))
+                .collect();
+              f.insert(BString::from_slice(b"path"), List(path));
+              out.push(Dict(f));
+            },
+            &DirNode(ref entries) => {
+              let mut names: Vec<&String> = entries.keys().collect();
+              names.sort();
+              for name in names.into_iter() {
+                prefix.push(name.clone());
+                walk(&entries[name], prefix, out);
+                prefix.pop();
+              };
+            },
+          }
+        }
+        let mut files: Vec<Bencode> = Vec::new();
+        let mut names: Vec<&String> = entries.keys().collect();
+        names.sort();
+        let mut prefix: Vec<String> = Vec::new();
+        for name in names.into_iter() {
+          prefix.push(name.clone());
+          walk(&entries[name], &mut prefix, &mut files);
+          prefix.pop();
+        };
+        info.insert(bs(b"files"), List(files));
+      },
+    };
+
+    // The top-level dict.
+    let mut root: BTreeMap<BString, Bencode> = BTreeMap::new();
+    if let Some(tier) = self.trackers.first().and_then(|t| t.first()) {
+      root.insert(bs(b"announce"), ByteString(tier.serialize().into_bytes()));
+    };
+    if !self.trackers.is_empty() {
+      let announce_list: Vec<Bencode> = self.trackers.iter()
+        .map(|tier| List(tier.iter()
+          .map(|u| ByteString(u.serialize().into_bytes()))
+          .collect()))
+        .collect();
+      root.insert(bs(b"announce-list"), List(announce_list));
+    };
+    if !self.nodes.is_empty() {
+      let nodes: Vec<Bencode> = self.nodes.iter()
+        .map(|&(ref host, port)| List(vec![
+          ByteString(format!("{}", host).into_bytes()),
+          Number(port as i64),
+        ]))
+        .collect();
+      root.insert(bs(b"nodes"), List(nodes));
+    };
+    if let Some(ref url) = self.urllist {
+      root.insert(bs(b"url-list"), ByteString(url.serialize().into_bytes()));
+    };
+    if !self.httpseeds.is_empty() {
+      let httpseeds: Vec<Bencode> = self.httpseeds.iter()
+        .map(|u| ByteString(u.serialize().into_bytes()))
+        .collect();
+      root.insert(bs(b"httpseeds"), List(httpseeds));
+    };
+    if let Some(date) = self.creation_date {
+      root.insert(bs(b"creation date"), Number(date));
+    };
+    if let Some(ref created_by) = self.created_by {
+      root.insert(bs(b"created by"), ByteString(created_by.clone().into_bytes()));
+    };
+    if let Some(ref comment) = self.comment {
+      root.insert(bs(b"comment"), ByteString(comment.clone().into_bytes()));
+    };
+    if let Some(ref encoding) = self.encoding {
+      root.insert(bs(b"encoding"), ByteString(encoding.clone().into_bytes()));
+    };
+    root.insert(bs(b"info"), Dict(info));
+
+    Some(Dict(root))
+  }
+
+  /// Encode this torrent and write it to `path` as a `.torrent` file. Returns
+  /// an `InvalidInput` error for v2 or hybrid torrents, which can't be
+  /// serialized (see `to_bencode`).
+  pub fn save_file(&self, path: &Path) -> io::Result<()> {
+    let ben = match self.to_bencode() {
+      Some(b) => b,
+      None    => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           "cannot serialize a v2 or hybrid torrent")),
+    };
+    let mut buf: Vec<u8> = Vec::new();
+    encode_bencode(&ben, &mut buf);
+    let mut f = try!(File::create(path));
+    f.write_all(&buf[..])
+  }
+
+  /// The torrent's files in piece order, each paired with its length in bytes.
+  /// A single-file torrent yields just the top-level name; a multi-file
+  /// torrent yields a depth-first walk of the directory tree, with directory
+  /// entries visited in sorted order. `contents` does not keep the order of
+  /// the original `files` list, so this matches the order `pieces` was
+  /// computed in only when that list was itself sorted.
+  fn file_list(&self) -> Vec<(PathBuf, u64)> {
+    fn collect(node: &TorrentDirTreeNode, prefix: PathBuf, out: &mut Vec<(PathBuf, u64)>) {
+      match node {
+        &FileNode(length, _)  => out.push((prefix, length)),
+        &DirNode(ref entries) => {
+          let mut names: Vec<&String> = entries.keys().collect();
+          names.sort();
+          for name in names.into_iter() {
+            collect(&entries[name], prefix.join(name), out);
+          };
+        },
+      }
+    }
+    let mut out: Vec<(PathBuf, u64)> = Vec::new();
+    collect(&self.contents, PathBuf::from(&self.filename), &mut out);
+    out
+  }
+
+  /// The torrent's creation time as a `chrono` UTC timestamp, if present.
+  #[cfg(feature = "chrono")]
+  pub fn creation_datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+    use chrono::TimeZone;
+    self.creation_date.map(|ts| chrono::Utc.timestamp(ts, 0))
+  }
+
+  /// Verify the torrent's data against the files under `root`.
+  ///
+  /// The files named in `contents` are streamed as one logical byte sequence,
+  /// split into `piece_length`-sized chunks (the last piece may be shorter),
+  /// SHA-1'd, and compared against `pieces`. The returned report records, per
+  /// piece, whether it matched and which file byte ranges it covers so callers
+  /// can tell *which* file is corrupt.
+  ///
+  /// Only v1 torrents can be verified this way; for v2 or hybrid torrents an
+  /// `InvalidInput` error is returned. (A v2-only torrent has empty `pieces`,
+  /// so a report for it would be vacuously complete.)
+  pub fn verify(&self, root: &Path) -> io::Result<VerifyReport> {
+    if self.version != TorrentVersion::V1 {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                "verify only supports v1 torrents"));
+    }
+
+    // The concatenated files with their start offsets in the logical stream.
+    let files = self.file_list();
+    let mut offsets: Vec<(PathBuf, u64, u64)> = Vec::new();
+    let mut total: u64 = 0;
+    for (path, length) in files.into_iter() {
+      offsets.push((path, total, length));
+      total += length;
+    };
+
+    let mut results: Vec<PieceResult> = Vec::new();
+    for (index, expected) in self.pieces.iter().enumerate() {
+      let piece_start = self.piece_length * index as u64;
+      let piece_end = match piece_start + self.piece_length {
+        e if e < total  => e,
+        _               => total,
+      };
+
+      let mut covered: Vec<FilePieceRange> = Vec::new();
+      let mut data: Vec<u8> = Vec::new();
+      for &(ref path, fstart, flen) in offsets.iter() {
+        let fend = fstart + flen;
+        let start = if piece_start > fstart { piece_start } else { fstart };
+        let end = if piece_end < fend { piece_end } else { fend };
+        if start >= end {
+          continue;
+        }
+        let mut f = try!(File::open(&root.join(path)));
+        try!(f.seek(SeekFrom::Start(start - fstart)));
+        let mut chunk = vec![0u8; (end - start) as usize];
+        // A single `read` may return a short count; fill the whole buffer so a
+        // valid file isn't mistaken for corrupt data due to trailing zeros.
+        let mut filled = 0;
+        while filled < chunk.len() {
+          match try!(f.read(&mut chunk[filled ..])) {
+            0 => break,
+            n => filled += n,
+          }
+        };
+        data.push_all(&chunk[.. filled]);
+        covered.push(FilePieceRange {
+          path:   path.clone(),
+          start:  start - fstart,
+          end:    end - fstart,
+        });
+      };
+
+      let matched = Sha1Hash::compute(&data[..]).hash == expected.hash;
+      results.push(PieceResult {
+        index:    index,
+        matched:  matched,
+        files:    covered,
+      });
+    };
+
+    Ok(VerifyReport {
+      pieces: results,
+    })
   }
 }
 